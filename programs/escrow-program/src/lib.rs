@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, CloseAccount, Mint, SetAuthority, TokenAccount, Transfer};
-use spl_token::instruction::AuthorityType;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, SetAuthority, TokenAccount, TokenInterface, TransferChecked,
+};
+use anchor_spl::token_2022::spl_token_2022::instruction::AuthorityType;
 
 declare_id!("1GdLS7WG2NsZX2Ba7yKCGMMb6mR9NDdrbQZtfLjm9C2");
 
@@ -8,18 +10,40 @@ declare_id!("1GdLS7WG2NsZX2Ba7yKCGMMb6mR9NDdrbQZtfLjm9C2");
 pub mod escrow_program {
     use super::*;
 
-    const ESCROW_PDA_SEED: &[u8] = b"escrow-pda-seed";
+    const VAULT_AUTHORITY_SEED: &[u8] = b"authority";
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>, fee_bps: u16) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.authority = *ctx.accounts.authority.key;
+        config.treasury = *ctx.accounts.treasury_token_account.to_account_info().key;
+        config.fee_bps = fee_bps;
+
+        Ok(())
+    }
+
+    pub fn update_fee(ctx: Context<UpdateFee>, fee_bps: u16) -> Result<()> {
+        ctx.accounts.config.fee_bps = fee_bps;
+
+        Ok(())
+    }
 
     pub fn initialize_escrow(
         ctx: Context<InitializeEscrow>,
-        _vault_account_bump: u8,
+        seed: u64,
         initializer_amount: u64,
         taker_amount: u64,
-    ) -> ProgramResult {
+        unlock_ts: Option<i64>,
+    ) -> Result<()> {
         let escrow_account = &mut ctx.accounts.escrow_account;
         escrow_account.initializer_key = *ctx.accounts.initializer.key;
+        escrow_account.seed = seed;
         escrow_account.initializer_amount = initializer_amount;
         escrow_account.taker_amount = taker_amount;
+        escrow_account.unlock_ts = unlock_ts;
+        escrow_account.mint_x = ctx.accounts.mint.key();
+        escrow_account.mint_y = ctx.accounts.receive_mint.key();
+        escrow_account.mint_x_decimals = ctx.accounts.mint.decimals;
+        escrow_account.mint_y_decimals = ctx.accounts.receive_mint.decimals;
 
         escrow_account.initializer_deposit_token_account = *ctx
             .accounts
@@ -33,107 +57,277 @@ pub mod escrow_program {
             .to_account_info()
             .key;
 
-        // Generate vault authority PDA
-        let (vault_authority, _) = Pubkey::find_program_address(&[ESCROW_PDA_SEED], ctx.program_id);
-
-        token::set_authority(
+        // Generate vault authority PDA, keyed by initializer and seed so a
+        // single user can have many concurrent escrows open at once.
+        let (vault_authority, vault_authority_bump) = Pubkey::find_program_address(
+            &[
+                VAULT_AUTHORITY_SEED,
+                ctx.accounts.initializer.key.as_ref(),
+                seed.to_le_bytes().as_ref(),
+            ],
+            ctx.program_id,
+        );
+        escrow_account.vault_authority_bump = vault_authority_bump;
+
+        token_interface::set_authority(
             ctx.accounts.as_set_authority_context(),
             AuthorityType::AccountOwner,
             Some(vault_authority),
         )?;
 
-        token::transfer(
+        token_interface::transfer_checked(
             ctx.accounts.as_transfer_to_pda_context(),
             initializer_amount,
+            ctx.accounts.mint.decimals,
         )
     }
 
-    pub fn cancel_escrow(ctx: Context<CancelEscrow>) -> ProgramResult {
-        let (_, vault_authority_bump) =
-            Pubkey::find_program_address(&[ESCROW_PDA_SEED], ctx.program_id);
-        let authority_seeds = &[ESCROW_PDA_SEED, &[vault_authority_bump]];
+    pub fn cancel_escrow(ctx: Context<CancelEscrow>) -> Result<()> {
+        let escrow_account = &ctx.accounts.escrow_account;
+        require!(
+            escrow_account
+                .unlock_ts
+                .is_none_or(|unlock_ts| ctx.accounts.clock.unix_timestamp >= unlock_ts),
+            ErrorCode::EscrowNotYetUnlocked
+        );
+
+        let seed = escrow_account.seed.to_le_bytes();
+        let authority_seeds = &[
+            VAULT_AUTHORITY_SEED,
+            escrow_account.initializer_key.as_ref(),
+            seed.as_ref(),
+            &[escrow_account.vault_authority_bump],
+        ];
 
         // transfer back from vault to initializer
-        token::transfer(
+        token_interface::transfer_checked(
             ctx.accounts
                 .as_transfer_to_initializer_context()
                 .with_signer(&[authority_seeds]),
             ctx.accounts.escrow_account.initializer_amount,
+            ctx.accounts.escrow_account.mint_x_decimals,
         )?;
 
         // close vault and escrow_account
-        token::close_account(
+        token_interface::close_account(
             ctx.accounts
                 .as_close_context()
                 .with_signer(&[authority_seeds]),
         )
     }
 
-    pub fn exchange(ctx: Context<Exchange>) -> ProgramResult {
-        let (_, vault_authority_bump) =
-            Pubkey::find_program_address(&[ESCROW_PDA_SEED], ctx.program_id);
-        let authority_seeds = &[ESCROW_PDA_SEED, &[vault_authority_bump]];
+    pub fn exchange(ctx: Context<Exchange>) -> Result<()> {
+        let escrow_account = &ctx.accounts.escrow_account;
+        require!(
+            escrow_account
+                .unlock_ts
+                .is_none_or(|unlock_ts| ctx.accounts.clock.unix_timestamp < unlock_ts),
+            ErrorCode::EscrowExpired
+        );
+
+        let seed = escrow_account.seed.to_le_bytes();
+        let authority_seeds = &[
+            VAULT_AUTHORITY_SEED,
+            escrow_account.initializer_key.as_ref(),
+            seed.as_ref(),
+            &[escrow_account.vault_authority_bump],
+        ];
+
+        let taker_amount = ctx.accounts.escrow_account.taker_amount;
+        let fee = (taker_amount as u128)
+            .checked_mul(ctx.accounts.config.fee_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64;
+
+        let mint_y_decimals = ctx.accounts.escrow_account.mint_y_decimals;
+        let mint_x_decimals = ctx.accounts.escrow_account.mint_x_decimals;
+
+        // protocol fee from taker to treasury
+        token_interface::transfer_checked(
+            ctx.accounts.as_transfer_fee_context(),
+            fee,
+            mint_y_decimals,
+        )?;
 
-        // transfer from taker to initializer
-        token::transfer(
+        // remainder from taker to initializer
+        token_interface::transfer_checked(
             ctx.accounts.as_transfer_to_initializer_context(),
-            ctx.accounts.escrow_account.taker_amount,
+            taker_amount - fee,
+            mint_y_decimals,
         )?;
 
         // transfer from initializer to taker
-        token::transfer(
+        token_interface::transfer_checked(
             ctx.accounts
                 .as_transfer_to_taker_context()
                 .with_signer(&[authority_seeds]),
             ctx.accounts.escrow_account.initializer_amount,
+            mint_x_decimals,
         )?;
 
-        token::close_account(
+        token_interface::close_account(
             ctx.accounts
                 .as_close_context()
                 .with_signer(&[authority_seeds]),
         )
     }
+
+    pub fn exchange_partial(ctx: Context<ExchangePartial>, fill_taker_amount: u64) -> Result<()> {
+        let escrow_account = &ctx.accounts.escrow_account;
+        require!(
+            escrow_account
+                .unlock_ts
+                .is_none_or(|unlock_ts| ctx.accounts.clock.unix_timestamp < unlock_ts),
+            ErrorCode::EscrowExpired
+        );
+        require!(
+            fill_taker_amount <= escrow_account.taker_amount,
+            ErrorCode::FillExceedsRemaining
+        );
+
+        let release = (escrow_account.initializer_amount as u128)
+            .checked_mul(fill_taker_amount as u128)
+            .unwrap()
+            .checked_div(escrow_account.taker_amount as u128)
+            .unwrap() as u64;
+        require!(release > 0, ErrorCode::FillTooSmall);
+
+        let initializer_key = escrow_account.initializer_key;
+        let seed = escrow_account.seed.to_le_bytes();
+        let vault_authority_bump = escrow_account.vault_authority_bump;
+        let mint_x_decimals = escrow_account.mint_x_decimals;
+        let mint_y_decimals = escrow_account.mint_y_decimals;
+
+        let authority_seeds = &[
+            VAULT_AUTHORITY_SEED,
+            initializer_key.as_ref(),
+            seed.as_ref(),
+            &[vault_authority_bump],
+        ];
+
+        let fee = (fill_taker_amount as u128)
+            .checked_mul(ctx.accounts.config.fee_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64;
+
+        // protocol fee from taker to treasury
+        token_interface::transfer_checked(ctx.accounts.as_transfer_fee_context(), fee, mint_y_decimals)?;
+
+        // remainder of the fill from taker to initializer
+        token_interface::transfer_checked(
+            ctx.accounts.as_transfer_to_initializer_context(),
+            fill_taker_amount - fee,
+            mint_y_decimals,
+        )?;
+
+        // release the proportional share of the vault to the taker
+        token_interface::transfer_checked(
+            ctx.accounts
+                .as_transfer_to_taker_context()
+                .with_signer(&[authority_seeds]),
+            release,
+            mint_x_decimals,
+        )?;
+
+        let escrow_account = &mut ctx.accounts.escrow_account;
+        escrow_account.initializer_amount -= release;
+        escrow_account.taker_amount -= fill_taker_amount;
+
+        if escrow_account.taker_amount == 0 {
+            token_interface::close_account(
+                ctx.accounts
+                    .as_close_context()
+                    .with_signer(&[authority_seeds]),
+            )?;
+            ctx.accounts
+                .escrow_account
+                .close(ctx.accounts.initializer.to_account_info())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut, signer)]
+    /// CHECK: signer that pays for the config account; its key is stored as
+    /// `config.authority`, its data is never read.
+    pub authority: AccountInfo<'info>,
+    #[account(
+        init,
+        seeds = [b"config".as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + 32 + 2 + 32,
+    )]
+    pub config: Account<'info, Config>,
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFee<'info> {
+    #[account(signer)]
+    /// CHECK: only used as a fee-authority signer, never read or written.
+    pub authority: AccountInfo<'info>,
+    #[account(mut, constraint = config.authority == *authority.key)]
+    pub config: Account<'info, Config>,
 }
 
 #[derive(Accounts)]
-#[instruction(vault_account_bump: u8, initializer_amount: u64)]
+#[instruction(seed: u64, initializer_amount: u64)]
 pub struct InitializeEscrow<'info> {
     #[account(mut, signer)]
+    /// CHECK: only used as the escrow's signer and rent payer.
     pub initializer: AccountInfo<'info>,
-    pub mint: Account<'info, Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// Mint the initializer wants to receive; its key and decimals are stored
+    /// as `mint_y` and `mint_y_decimals`.
+    pub receive_mint: InterfaceAccount<'info, Mint>,
     #[account(
         init,
-        seeds = [b"token-seed".as_ref()],
-        bump = vault_account_bump,
+        seeds = [b"vault".as_ref(), initializer.key.as_ref(), seed.to_le_bytes().as_ref()],
+        bump,
         payer = initializer,
         token::mint = mint,
         token::authority = initializer,
+        token::token_program = token_program,
     )]
-    pub vault_account: Account<'info, TokenAccount>,
+    pub vault_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         constraint =
             initializer_deposit_token_account.amount >= initializer_amount,
     )]
-    pub initializer_deposit_token_account: Account<'info, TokenAccount>,
-    pub initializer_receive_token_account: Account<'info, TokenAccount>,
-    #[account(zero)]
-    pub escrow_account: ProgramAccount<'info, EscrowAccount>,
+    pub initializer_deposit_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub initializer_receive_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        seeds = [b"escrow".as_ref(), initializer.key.as_ref(), seed.to_le_bytes().as_ref()],
+        bump,
+        payer = initializer,
+        space = 8 + 32 + 8 + 1 + 32 + 32 + 32 + 32 + 1 + 1 + 8 + 8 + 1 + 8,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
-    pub token_program: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 impl<'info> InitializeEscrow<'info> {
-    fn as_transfer_to_pda_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        let cpi_accounts = Transfer {
+    fn as_transfer_to_pda_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
             from: self.initializer_deposit_token_account.to_account_info(),
+            mint: self.mint.to_account_info(),
             to: self.vault_account.to_account_info(),
             authority: self.initializer.clone(),
         };
 
-        CpiContext::new(self.token_program.clone(), cpi_accounts)
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
     }
 
     fn as_set_authority_context(&self) -> CpiContext<'_, '_, '_, 'info, SetAuthority<'info>> {
@@ -150,14 +344,24 @@ impl<'info> InitializeEscrow<'info> {
 #[derive(Accounts)]
 pub struct CancelEscrow<'info> {
     #[account(mut, signer)]
+    /// CHECK: only used as the escrow's signer and refund destination.
     pub initializer: AccountInfo<'info>,
+    #[account(constraint = mint.key() == escrow_account.mint_x)]
+    pub mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
-    pub initializer_deposit_token_account: Account<'info, TokenAccount>,
+    pub initializer_deposit_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub vault_account: Account<'info, TokenAccount>,
+    pub vault_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that owns the vault token account; never read, only used as a CPI signer.
     pub vault_authority: AccountInfo<'info>,
     #[account(
         mut,
+        seeds = [
+            b"escrow".as_ref(),
+            escrow_account.initializer_key.as_ref(),
+            escrow_account.seed.to_le_bytes().as_ref(),
+        ],
+        bump,
         constraint =
             escrow_account.initializer_key == *initializer.key,
         constraint =
@@ -166,19 +370,23 @@ pub struct CancelEscrow<'info> {
             .to_account_info().key,
         close = initializer,
     )]
-    pub escrow_account: ProgramAccount<'info, EscrowAccount>,
-    pub token_program: AccountInfo<'info>,
+    pub escrow_account: Account<'info, EscrowAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub clock: Sysvar<'info, Clock>,
 }
 
 impl<'info> CancelEscrow<'info> {
-    fn as_transfer_to_initializer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        let cpi_accounts = Transfer {
+    fn as_transfer_to_initializer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
             from: self.vault_account.to_account_info(),
+            mint: self.mint.to_account_info(),
             to: self.initializer_deposit_token_account.to_account_info(),
             authority: self.vault_authority.clone(),
         };
 
-        CpiContext::new(self.token_program.clone(), cpi_accounts)
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
     }
 
     fn as_close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
@@ -196,19 +404,31 @@ impl<'info> CancelEscrow<'info> {
 #[derive(Accounts)]
 pub struct Exchange<'info> {
     #[account(signer)]
+    /// CHECK: only used as the taker's signer.
     pub taker: AccountInfo<'info>,
+    #[account(constraint = mint_x.key() == escrow_account.mint_x)]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(constraint = mint_y.key() == escrow_account.mint_y)]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(mut, constraint = taker_deposit_token_account.mint == escrow_account.mint_y)]
+    pub taker_deposit_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = taker_receive_token_account.mint == escrow_account.mint_x)]
+    pub taker_receive_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = initializer_deposit_token_account.mint == escrow_account.mint_x)]
+    pub initializer_deposit_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = initializer_receive_token_account.mint == escrow_account.mint_y)]
+    pub initializer_receive_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub taker_deposit_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub taker_receive_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub initializer_deposit_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub initializer_receive_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
+    /// CHECK: only used as the escrow's refund/close destination.
     pub initializer: AccountInfo<'info>,
     #[account(
         mut,
+        seeds = [
+            b"escrow".as_ref(),
+            escrow_account.initializer_key.as_ref(),
+            escrow_account.seed.to_le_bytes().as_ref(),
+        ],
+        bump,
         constraint =
             escrow_account.taker_amount <= taker_deposit_token_account.amount,
         constraint =
@@ -225,32 +445,155 @@ pub struct Exchange<'info> {
             escrow_account.initializer_key == *initializer.key,
         close = initializer,
     )]
-    pub escrow_account: ProgramAccount<'info, EscrowAccount>,
+    pub escrow_account: Account<'info, EscrowAccount>,
     #[account(mut)]
-    pub vault_account: Account<'info, TokenAccount>,
+    pub vault_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that owns the vault token account; never read, only used as a CPI signer.
     pub vault_authority: AccountInfo<'info>,
-    pub token_program: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub clock: Sysvar<'info, Clock>,
+    #[account(seeds = [b"config".as_ref()], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, constraint = treasury_token_account.key() == config.treasury)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
 }
 
 impl<'info> Exchange<'info> {
-    fn as_transfer_to_initializer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        let cpi_accounts = Transfer {
+    fn as_transfer_to_initializer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
             from: self.taker_deposit_token_account.to_account_info(),
-            to: self.initializer_deposit_token_account.to_account_info(),
+            mint: self.mint_y.to_account_info(),
+            to: self.initializer_receive_token_account.to_account_info(),
             authority: self.taker.clone(),
         };
 
         CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
     }
 
-    fn as_transfer_to_taker_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        let cpi_accounts = Transfer {
+    fn as_transfer_fee_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
+            from: self.taker_deposit_token_account.to_account_info(),
+            mint: self.mint_y.to_account_info(),
+            to: self.treasury_token_account.to_account_info(),
+            authority: self.taker.clone(),
+        };
+
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn as_transfer_to_taker_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
             from: self.vault_account.to_account_info(),
+            mint: self.mint_x.to_account_info(),
             to: self.taker_receive_token_account.to_account_info(),
             authority: self.vault_authority.clone(),
         };
 
-        CpiContext::new(self.token_program.clone(), cpi_accounts)
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn as_close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.vault_account.to_account_info(),
+            destination: self.initializer.clone(),
+            authority: self.vault_authority.clone(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+pub struct ExchangePartial<'info> {
+    #[account(signer)]
+    /// CHECK: only used as the taker's signer.
+    pub taker: AccountInfo<'info>,
+    #[account(constraint = mint_x.key() == escrow_account.mint_x)]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(constraint = mint_y.key() == escrow_account.mint_y)]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(mut, constraint = taker_deposit_token_account.mint == escrow_account.mint_y)]
+    pub taker_deposit_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = taker_receive_token_account.mint == escrow_account.mint_x)]
+    pub taker_receive_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = initializer_deposit_token_account.mint == escrow_account.mint_x)]
+    pub initializer_deposit_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = initializer_receive_token_account.mint == escrow_account.mint_y)]
+    pub initializer_receive_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    /// CHECK: only used as the escrow's refund/close destination.
+    pub initializer: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow".as_ref(),
+            escrow_account.initializer_key.as_ref(),
+            escrow_account.seed.to_le_bytes().as_ref(),
+        ],
+        bump,
+        constraint =
+            escrow_account
+            .initializer_deposit_token_account == *initializer_deposit_token_account
+            .to_account_info()
+            .key,
+        constraint =
+            escrow_account
+            .initializer_receive_token_account == *initializer_receive_token_account
+            .to_account_info()
+            .key,
+        constraint =
+            escrow_account.initializer_key == *initializer.key,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    #[account(mut)]
+    pub vault_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that owns the vault token account; never read, only used as a CPI signer.
+    pub vault_authority: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub clock: Sysvar<'info, Clock>,
+    #[account(seeds = [b"config".as_ref()], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, constraint = treasury_token_account.key() == config.treasury)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+}
+
+impl<'info> ExchangePartial<'info> {
+    fn as_transfer_to_initializer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
+            from: self.taker_deposit_token_account.to_account_info(),
+            mint: self.mint_y.to_account_info(),
+            to: self.initializer_receive_token_account.to_account_info(),
+            authority: self.taker.clone(),
+        };
+
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn as_transfer_fee_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
+            from: self.taker_deposit_token_account.to_account_info(),
+            mint: self.mint_y.to_account_info(),
+            to: self.treasury_token_account.to_account_info(),
+            authority: self.taker.clone(),
+        };
+
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn as_transfer_to_taker_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
+            from: self.vault_account.to_account_info(),
+            mint: self.mint_x.to_account_info(),
+            to: self.taker_receive_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
     }
 
     fn as_close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
@@ -269,12 +612,54 @@ impl<'info> Exchange<'info> {
 pub struct EscrowAccount {
     /// Key to authorize actions properly.
     pub initializer_key: Pubkey,
+    /// Caller-chosen seed this escrow's PDAs were derived from, letting one
+    /// initializer keep several escrows open at the same time.
+    pub seed: u64,
+    /// Bump of the vault authority PDA, kept so `cancel_escrow`/`exchange`
+    /// can reconstruct the signer seeds without recomputing them.
+    pub vault_authority_bump: u8,
     /// Initializer's deposit account.
     pub initializer_deposit_token_account: Pubkey,
     /// Initializer's receive account.
     pub initializer_receive_token_account: Pubkey,
+    /// Mint of the token the initializer deposited into the vault.
+    pub mint_x: Pubkey,
+    /// Mint of the token the initializer wants to receive from the taker.
+    pub mint_y: Pubkey,
+    /// Decimals of `mint_x`, carried so checked transfers don't need to
+    /// re-read the mint account.
+    pub mint_x_decimals: u8,
+    /// Decimals of `mint_y`, carried so checked transfers don't need to
+    /// re-read the mint account.
+    pub mint_y_decimals: u8,
     /// How many tokens the initializer should send to taker.
     pub initializer_amount: u64,
     /// How many tokens the initializer should receive from the taker.
     pub taker_amount: u64,
+    /// Unix timestamp after which the deal expires: `exchange` only works
+    /// before it, `cancel_escrow` only works at or after it. `None` disables
+    /// the time lock, so both instructions are allowed at any time.
+    pub unlock_ts: Option<i64>,
+}
+
+#[account]
+pub struct Config {
+    /// Key allowed to update `fee_bps`.
+    pub authority: Pubkey,
+    /// Protocol fee charged on `exchange`, in basis points.
+    pub fee_bps: u16,
+    /// Token account the fee is sent to.
+    pub treasury: Pubkey,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The escrow has expired and can no longer be exchanged")]
+    EscrowExpired,
+    #[msg("The escrow can only be cancelled once it has expired")]
+    EscrowNotYetUnlocked,
+    #[msg("fill_taker_amount exceeds the escrow's remaining taker_amount")]
+    FillExceedsRemaining,
+    #[msg("This fill is too small and would release zero tokens")]
+    FillTooSmall,
 }